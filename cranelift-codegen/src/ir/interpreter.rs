@@ -0,0 +1,485 @@
+//! A straightforward interpreter for `ir::Function`.
+//!
+//! This is a small abstract machine that executes a `Function` directly on the IR, without
+//! going through an `isa`. It exists to let other passes check themselves: a legalization such
+//! as `expand_global_value` can be verified to preserve semantics by interpreting the function
+//! before and after the transformation and comparing the results.
+
+use crate::ir::condcodes::IntCC;
+use crate::ir::immediates::Offset32;
+use crate::ir::instructions::InstructionData;
+use crate::ir::stackslot::StackSlotData;
+use crate::ir::{
+    Ebb, Function, GlobalValueData, Inst, MemFlags, Opcode, StackSlot, TrapCode, Type, Value,
+};
+use std::collections::HashMap;
+
+/// A concrete value produced or consumed while interpreting a function.
+///
+/// Integers of every width are stored sign-extended to 64 bits; the `Type` recorded alongside an
+/// `Interpreter`'s bindings (not the `DataValue` itself) says how many of those bits matter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataValue {
+    I(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl DataValue {
+    fn to_i64(self) -> i64 {
+        match self {
+            DataValue::I(v) => v,
+            DataValue::F32(_) | DataValue::F64(_) => panic!("expected an integer value"),
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            DataValue::I(v) => v == 0,
+            DataValue::F32(v) => v == 0.0,
+            DataValue::F64(v) => v == 0.0,
+        }
+    }
+}
+
+/// Either the return values of the interpreted function, or the trap that stopped it.
+pub type InterpreterResult = Result<Vec<DataValue>, TrapCode>;
+
+/// Interpret `func`, called with `args`, and return its result values or a trap.
+pub fn interpret(func: &Function, args: &[DataValue]) -> InterpreterResult {
+    Interpreter::new(func).run(args)
+}
+
+/// The byte-addressable memory backing a function's stack slots, simulated as a single flat
+/// buffer. Global-value loads are resolved against the same buffer, rooted at offset 0 for the
+/// (simulated) `vmctx`.
+struct Memory {
+    bytes: Vec<u8>,
+    slot_offsets: HashMap<StackSlot, usize>,
+}
+
+impl Memory {
+    fn new(func: &Function) -> Memory {
+        let mut bytes = Vec::new();
+        let mut slot_offsets = HashMap::new();
+        for ss in func.stack_slots.keys() {
+            let StackSlotData { size, .. } = func.stack_slots[ss];
+            slot_offsets.insert(ss, bytes.len());
+            bytes.resize(bytes.len() + size as usize, 0);
+        }
+        Memory { bytes, slot_offsets }
+    }
+
+    fn stack_addr(&self, ss: StackSlot, offset: Offset32) -> Result<usize, TrapCode> {
+        let base = self.slot_offsets[&ss];
+        let addr = base as i64 + Into::<i64>::into(offset);
+        if addr < 0 || addr as usize > self.bytes.len() {
+            return Err(TrapCode::StackOutOfBounds);
+        }
+        Ok(addr as usize)
+    }
+
+    fn load(&self, addr: usize, ty: Type) -> Result<DataValue, TrapCode> {
+        let size = ty.bytes() as usize;
+        let end = addr.checked_add(size).ok_or(TrapCode::HeapOutOfBounds)?;
+        if end > self.bytes.len() {
+            return Err(TrapCode::HeapOutOfBounds);
+        }
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(&self.bytes[addr..end]);
+        let bits = u64::from_le_bytes(buf);
+        Ok(if ty.is_float() {
+            if size == 4 {
+                DataValue::F32(f32::from_bits(bits as u32))
+            } else {
+                DataValue::F64(f64::from_bits(bits))
+            }
+        } else {
+            let shift = 64 - size * 8;
+            DataValue::I(((bits << shift) as i64) >> shift)
+        })
+    }
+
+    fn store(&mut self, addr: usize, ty: Type, value: DataValue) -> Result<(), TrapCode> {
+        let size = ty.bytes() as usize;
+        let end = addr.checked_add(size).ok_or(TrapCode::HeapOutOfBounds)?;
+        if end > self.bytes.len() {
+            return Err(TrapCode::HeapOutOfBounds);
+        }
+        let bits: u64 = match value {
+            DataValue::I(v) => v as u64,
+            DataValue::F32(v) => v.to_bits() as u64,
+            DataValue::F64(v) => v.to_bits(),
+        };
+        self.bytes[addr..end].copy_from_slice(&bits.to_le_bytes()[..size]);
+        Ok(())
+    }
+}
+
+/// Walks a `Function`'s EBBs, interpreting one instruction at a time.
+struct Interpreter<'a> {
+    func: &'a Function,
+    env: HashMap<Value, DataValue>,
+    memory: Memory,
+}
+
+/// What to do once the terminator of the current EBB has been interpreted.
+enum Control {
+    Jump(Ebb, Vec<DataValue>),
+    Return(Vec<DataValue>),
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(func: &'a Function) -> Interpreter<'a> {
+        Interpreter {
+            func,
+            env: HashMap::new(),
+            memory: Memory::new(func),
+        }
+    }
+
+    fn run(&mut self, args: &[DataValue]) -> InterpreterResult {
+        let entry = self.func
+            .layout
+            .entry_block()
+            .expect("function must have an entry block to interpret");
+        self.bind_ebb_params(entry, args);
+
+        let mut ebb = entry;
+        loop {
+            match self.run_ebb(ebb)? {
+                Control::Jump(dest, args) => {
+                    self.bind_ebb_params(dest, &args);
+                    ebb = dest;
+                }
+                Control::Return(results) => return Ok(results),
+            }
+        }
+    }
+
+    fn bind_ebb_params(&mut self, ebb: Ebb, args: &[DataValue]) {
+        for (&param, &arg) in self.func.dfg.ebb_params(ebb).iter().zip(args) {
+            self.env.insert(param, arg);
+        }
+    }
+
+    fn run_ebb(&mut self, ebb: Ebb) -> Result<Control, TrapCode> {
+        for inst in self.func.layout.ebb_insts(ebb) {
+            if let Some(control) = self.step(ebb, inst)? {
+                return Ok(control);
+            }
+        }
+        unreachable!("a well-formed EBB always ends in a terminator");
+    }
+
+    /// Interpret a single instruction, returning `Some(Control)` if it was a terminator.
+    fn step(&mut self, ebb: Ebb, inst: Inst) -> Result<Option<Control>, TrapCode> {
+        let dfg = &self.func.dfg;
+        let opcode = dfg[inst].opcode();
+        let args: Vec<DataValue> = dfg.inst_args(inst)
+            .iter()
+            .map(|&v| self.env[&v])
+            .collect();
+
+        match opcode {
+            Opcode::Iconst => {
+                if let InstructionData::UnaryImm { imm, .. } = dfg[inst] {
+                    self.set_result(inst, DataValue::I(imm.into()));
+                }
+            }
+            Opcode::F32const => {
+                if let InstructionData::UnaryIeee32 { imm, .. } = dfg[inst] {
+                    self.set_result(inst, DataValue::F32(f32::from_bits(imm.bits())));
+                }
+            }
+            Opcode::F64const => {
+                if let InstructionData::UnaryIeee64 { imm, .. } = dfg[inst] {
+                    self.set_result(inst, DataValue::F64(f64::from_bits(imm.bits())));
+                }
+            }
+            Opcode::Iadd => {
+                self.set_result(inst, DataValue::I(args[0].to_i64().wrapping_add(args[1].to_i64())));
+            }
+            Opcode::Isub => {
+                self.set_result(inst, DataValue::I(args[0].to_i64().wrapping_sub(args[1].to_i64())));
+            }
+            Opcode::Imul => {
+                self.set_result(inst, DataValue::I(args[0].to_i64().wrapping_mul(args[1].to_i64())));
+            }
+            Opcode::Sdiv | Opcode::Udiv => {
+                let divisor = args[1].to_i64();
+                if divisor == 0 {
+                    return Err(TrapCode::IntegerDivisionByZero);
+                }
+                self.set_result(inst, DataValue::I(args[0].to_i64().wrapping_div(divisor)));
+            }
+            Opcode::IaddImm => {
+                if let InstructionData::BinaryImm { imm, .. } = dfg[inst] {
+                    let imm: i64 = imm.into();
+                    self.set_result(inst, DataValue::I(args[0].to_i64().wrapping_add(imm)));
+                }
+            }
+            Opcode::Icmp => {
+                if let InstructionData::IntCompare { cond, .. } = dfg[inst] {
+                    let (a, b) = (args[0].to_i64(), args[1].to_i64());
+                    let (ua, ub) = (a as u64, b as u64);
+                    let result = match cond {
+                        IntCC::Equal => a == b,
+                        IntCC::NotEqual => a != b,
+                        IntCC::SignedLessThan => a < b,
+                        IntCC::SignedGreaterThanOrEqual => a >= b,
+                        IntCC::SignedGreaterThan => a > b,
+                        IntCC::SignedLessThanOrEqual => a <= b,
+                        IntCC::UnsignedLessThan => ua < ub,
+                        IntCC::UnsignedGreaterThanOrEqual => ua >= ub,
+                        IntCC::UnsignedGreaterThan => ua > ub,
+                        IntCC::UnsignedLessThanOrEqual => ua <= ub,
+                        IntCC::Overflow | IntCC::NotOverflow => {
+                            panic!("interpreter does not support overflow conditions")
+                        }
+                    };
+                    self.set_result(inst, DataValue::I(result as i64));
+                }
+            }
+            Opcode::Select => {
+                self.set_result(inst, if !args[0].is_zero() { args[1] } else { args[2] });
+            }
+            Opcode::Load | Opcode::StackLoad => {
+                let ty = dfg.value_type(dfg.first_result(inst));
+                let addr = self.address_of(inst, ebb, ty, &args)?;
+                let value = self.memory.load(addr, ty)?;
+                self.set_result(inst, value);
+            }
+            Opcode::Store | Opcode::StackStore => {
+                let stored = args[0];
+                let ty = dfg.value_type(dfg.inst_args(inst)[0]);
+                let addr = self.address_of(inst, ebb, ty, &args[1..])?;
+                self.memory.store(addr, ty, stored)?;
+            }
+            Opcode::GlobalValue => {
+                if let InstructionData::UnaryGlobalValue { global_value, .. } = dfg[inst] {
+                    let addr = self.global_value_addr(global_value)?;
+                    self.set_result(inst, DataValue::I(addr as i64));
+                }
+            }
+            Opcode::Jump => {
+                if let InstructionData::Jump { destination, ref args, .. } = dfg[inst] {
+                    let args = self.resolve(args.as_slice(&dfg.value_lists));
+                    return Ok(Some(Control::Jump(destination, args)));
+                }
+            }
+            Opcode::Brz | Opcode::Brnz => {
+                if let InstructionData::Branch { destination, ref args, .. } = dfg[inst] {
+                    let values = args.as_slice(&dfg.value_lists);
+                    let test = self.env[&values[0]];
+                    let taken = if opcode == Opcode::Brz { test.is_zero() } else { !test.is_zero() };
+                    if taken {
+                        return Ok(Some(Control::Jump(destination, self.resolve(&values[1..]))));
+                    }
+                    // `brz`/`brnz` is a terminator: when not taken, control falls through to the
+                    // next EBB in layout order, exactly as `flowgraph::branch_destinations` models
+                    // it for the static CFG.
+                    let fallthrough = self.func
+                        .layout
+                        .next_ebb(ebb)
+                        .expect("brz/brnz needs a fallthrough EBB");
+                    return Ok(Some(Control::Jump(fallthrough, Vec::new())));
+                }
+            }
+            Opcode::BrTable => {
+                if let InstructionData::BranchTable { arg, table, .. } = dfg[inst] {
+                    let index = self.env[&arg].to_i64() as usize;
+                    let dest = self.func.jump_tables[table]
+                        .iter()
+                        .nth(index)
+                        .cloned()
+                        .unwrap_or_else(|| {
+                            self.func.layout.next_ebb(ebb).expect("br_table needs a fallthrough")
+                        });
+                    return Ok(Some(Control::Jump(dest, Vec::new())));
+                }
+            }
+            Opcode::Return => {
+                return Ok(Some(Control::Return(args)));
+            }
+            Opcode::Trap => {
+                if let InstructionData::Trap { code, .. } = dfg[inst] {
+                    return Err(code);
+                }
+            }
+            _ => panic!("interpreter does not support opcode {}", opcode),
+        }
+
+        Ok(None)
+    }
+
+    fn resolve(&self, values: &[Value]) -> Vec<DataValue> {
+        values.iter().map(|v| self.env[v]).collect()
+    }
+
+    fn set_result(&mut self, inst: Inst, value: DataValue) {
+        let result = self.func.dfg.first_result(inst);
+        let ty = self.func.dfg.value_type(result);
+        // Keep integers sign-extended from their type's width, as `DataValue` documents.
+        let value = match value {
+            DataValue::I(v) if ty.is_int() && ty.bits() < 64 => {
+                let shift = 64 - u32::from(ty.bits());
+                DataValue::I((v << shift) >> shift)
+            }
+            other => other,
+        };
+        self.env.insert(result, value);
+    }
+
+    /// Compute the byte offset a `load`/`store`/`stack_load`/`stack_store` reads or writes, by
+    /// resolving its base address (an EBB value for `load`/`store`, a `StackSlot` for
+    /// `stack_load`/`stack_store`) plus its immediate offset.
+    ///
+    /// `endianness`/`notrap` in a `load`/`store`'s `MemFlags` are ignored: this interpreter
+    /// always reads and writes little-endian (matching `Memory::load`/`store`) and never treats
+    /// a `notrap` access specially, since nothing here models a faulting host memory access for
+    /// it to suppress. `aligned` is honored: an access flagged `aligned` traps if its runtime
+    /// address isn't actually a multiple of the access size, the same contract the legalized
+    /// form would be relying on to pick faster-but-alignment-sensitive instructions.
+    fn address_of(
+        &self,
+        inst: Inst,
+        _ebb: Ebb,
+        ty: Type,
+        base_args: &[DataValue],
+    ) -> Result<usize, TrapCode> {
+        match self.func.dfg[inst] {
+            InstructionData::Load { offset, flags, .. } |
+            InstructionData::Store { offset, flags, .. } => {
+                let base = base_args[0].to_i64();
+                let addr = base + Into::<i64>::into(offset);
+                if addr < 0 {
+                    return Err(TrapCode::HeapOutOfBounds);
+                }
+                let addr = addr as usize;
+                self.check_alignment(addr, ty, flags)?;
+                Ok(addr)
+            }
+            InstructionData::StackLoad { stack_slot, offset, .. } |
+            InstructionData::StackStore { stack_slot, offset, .. } => {
+                self.memory.stack_addr(stack_slot, offset)
+            }
+            _ => unreachable!("address_of called on a non-memory instruction"),
+        }
+    }
+
+    /// Trap if `flags` asserts `aligned` but `addr` isn't actually aligned to `ty`'s size.
+    fn check_alignment(&self, addr: usize, ty: Type, flags: MemFlags) -> Result<(), TrapCode> {
+        if flags.aligned() && addr % ty.bytes() as usize != 0 {
+            // No dedicated misalignment trap code is defined in this tree; `HeapOutOfBounds` is
+            // the closest existing variant for "the access wasn't where the flags promised".
+            return Err(TrapCode::HeapOutOfBounds);
+        }
+        Ok(())
+    }
+
+    /// Resolve the address a `GlobalValue` refers to, following `base` chains. The verifier
+    /// guarantees these chains terminate, so this never loops.
+    ///
+    /// A `Load` global whose address is out of bounds traps rather than silently reading as
+    /// address 0, so a genuinely broken global-value chain compares unequal to whatever trap
+    /// the legalized (post-`expand_global_value`) form produces, instead of comparing equal to
+    /// a bogus-but-successful result.
+    fn global_value_addr(&self, gv: crate::ir::GlobalValue) -> Result<usize, TrapCode> {
+        match self.func.global_values[gv] {
+            GlobalValueData::VMContext => Ok(0),
+            GlobalValueData::IAddImm { base, offset, .. } => {
+                Ok((self.global_value_addr(base)? as i64 + Into::<i64>::into(offset)) as usize)
+            }
+            GlobalValueData::Load { base, offset, global_type } => {
+                let base_addr = self.global_value_addr(base)?;
+                let addr = (base_addr as i64 + Into::<i64>::into(offset)) as usize;
+                Ok(self.memory.load(addr, global_type)?.to_i64() as usize)
+            }
+            GlobalValueData::Symbol { .. } => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpret, DataValue};
+    use crate::ir::instructions::{InstructionData, Opcode};
+    use crate::ir::{Function, GlobalValueData, TrapCode, ValueList};
+    use crate::ir::types;
+
+    #[test]
+    fn brz_not_taken_falls_through() {
+        // ebb0: v0 = iconst.i32 1; brz v0, ebb1  -- not taken, since v0 != 0.
+        // ebb1: v1 = iconst.i32 99; return v1.
+        let mut func = Function::new();
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+
+        let iconst0 = func.dfg.make_inst(InstructionData::UnaryImm {
+                                              opcode: Opcode::Iconst,
+                                              imm: 1.into(),
+                                          });
+        let v0 = func.dfg.append_result(iconst0, types::I32);
+        let mut brz_args = ValueList::new();
+        brz_args.push(v0, &mut func.dfg.value_lists);
+        let brz = func.dfg.make_inst(InstructionData::Branch {
+                                          opcode: Opcode::Brz,
+                                          args: brz_args,
+                                          destination: ebb1,
+                                      });
+
+        let iconst1 = func.dfg.make_inst(InstructionData::UnaryImm {
+                                              opcode: Opcode::Iconst,
+                                              imm: 99.into(),
+                                          });
+        let v1 = func.dfg.append_result(iconst1, types::I32);
+        let mut ret_args = ValueList::new();
+        ret_args.push(v1, &mut func.dfg.value_lists);
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ret_args,
+                                      });
+
+        func.layout.append_ebb(ebb0);
+        func.layout.append_inst(iconst0, ebb0);
+        func.layout.append_inst(brz, ebb0);
+        func.layout.append_ebb(ebb1);
+        func.layout.append_inst(iconst1, ebb1);
+        func.layout.append_inst(ret, ebb1);
+
+        assert_eq!(interpret(&func, &[]), Ok(vec![DataValue::I(99)]));
+    }
+
+    #[test]
+    fn global_value_load_out_of_bounds_traps() {
+        // A `load` global reading far past the (empty) simulated memory must trap, not
+        // silently resolve to address 0.
+        let mut func = Function::new();
+        let vmctx = func.global_values.push(GlobalValueData::VMContext);
+        let broken_load = func.global_values.push(GlobalValueData::Load {
+                                                        base: vmctx,
+                                                        offset: 1_000.into(),
+                                                        global_type: types::I32,
+                                                    });
+
+        let ebb0 = func.dfg.make_ebb();
+        let gv_inst = func.dfg.make_inst(InstructionData::UnaryGlobalValue {
+                                              opcode: Opcode::GlobalValue,
+                                              global_value: broken_load,
+                                          });
+        let v0 = func.dfg.append_result(gv_inst, types::I32);
+        let mut ret_args = ValueList::new();
+        ret_args.push(v0, &mut func.dfg.value_lists);
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ret_args,
+                                      });
+
+        func.layout.append_ebb(ebb0);
+        func.layout.append_inst(gv_inst, ebb0);
+        func.layout.append_inst(ret, ebb0);
+
+        assert_eq!(interpret(&func, &[]), Err(TrapCode::HeapOutOfBounds));
+    }
+}
@@ -11,6 +11,7 @@ mod globalvalue;
 mod heap;
 pub mod immediates;
 pub mod instructions;
+pub mod interpreter;
 pub mod jumptable;
 pub mod layout;
 mod libcall;
@@ -38,6 +39,7 @@ pub use crate::ir::heap::{HeapData, HeapStyle};
 pub use crate::ir::instructions::{
     InstructionData, Opcode, ValueList, ValueListPool, VariableArgs,
 };
+pub use crate::ir::interpreter::{interpret, DataValue, InterpreterResult};
 pub use crate::ir::jumptable::JumpTableData;
 pub use crate::ir::layout::Layout;
 pub use crate::ir::libcall::{get_libcall_funcref, get_probestack_funcref, LibCall};
@@ -0,0 +1,198 @@
+//! Dominator tree computation.
+//!
+//! The `DominatorTree` computes immediate dominators at EBB granularity using the iterative
+//! algorithm of Cooper, Harvey and Kennedy ("A Simple, Fast Dominance Algorithm"), then extends
+//! dominance queries down to instruction granularity using layout order within an EBB.
+
+use flowgraph::ControlFlowGraph;
+use ir::{Ebb, Function, Inst, Layout};
+use std::collections::{HashMap, HashSet};
+
+/// The dominator tree for a single function, computed from its `ControlFlowGraph`.
+pub struct DominatorTree {
+    // EBBs reachable from the entry block, in postorder.
+    ebbs_postorder: Vec<Ebb>,
+    // Reverse postorder number of each reachable EBB; used to walk idom chains efficiently.
+    rpo_number: HashMap<Ebb, u32>,
+    // Immediate dominator of each reachable EBB. The entry block is its own immediate dominator.
+    idom: HashMap<Ebb, Ebb>,
+    // Layout position of each instruction within its EBB, used for same-EBB dominance checks.
+    inst_seq: HashMap<Inst, u32>,
+}
+
+impl DominatorTree {
+    /// Allocate a new blank dominator tree.
+    pub fn new() -> DominatorTree {
+        DominatorTree {
+            ebbs_postorder: Vec::new(),
+            rpo_number: HashMap::new(),
+            idom: HashMap::new(),
+            inst_seq: HashMap::new(),
+        }
+    }
+
+    /// Build the dominator tree of `func`, given its control flow graph `cfg`.
+    pub fn with_function(func: &Function, cfg: &ControlFlowGraph) -> DominatorTree {
+        let mut domtree = DominatorTree::new();
+        domtree.compute(func, cfg);
+        domtree
+    }
+
+    /// Recompute the dominator tree of `func` from its control flow graph `cfg`, discarding any
+    /// previous contents.
+    pub fn compute(&mut self, func: &Function, cfg: &ControlFlowGraph) {
+        self.ebbs_postorder.clear();
+        self.rpo_number.clear();
+        self.idom.clear();
+        self.inst_seq.clear();
+
+        for ebb in func.layout.ebbs() {
+            for (seq, inst) in func.layout.ebb_insts(ebb).enumerate() {
+                self.inst_seq.insert(inst, seq as u32);
+            }
+        }
+
+        let entry = match func.layout.entry_block() {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        self.number_in_postorder(entry, cfg);
+        for (rpo, &ebb) in self.ebbs_postorder.iter().rev().enumerate() {
+            self.rpo_number.insert(ebb, rpo as u32);
+        }
+
+        // The entry block has no predecessors to intersect; it dominates itself.
+        self.idom.insert(entry, entry);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // Process EBBs in reverse postorder, which visits an EBB's predecessors (other than
+            // back edges) before the EBB itself on the first pass, making the fixpoint converge
+            // quickly.
+            for &ebb in self.ebbs_postorder.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for pred in cfg.predecessors(ebb) {
+                    if !self.idom.contains_key(&pred.ebb) {
+                        // Predecessor is unreachable or not yet processed this pass.
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred.ebb,
+                        Some(candidate) => self.intersect(candidate, pred.ebb),
+                    });
+                }
+                if let Some(new_idom) = new_idom {
+                    if self.idom.get(&ebb) != Some(&new_idom) {
+                        self.idom.insert(ebb, new_idom);
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Depth-first postorder numbering of the EBBs reachable from `entry`, following CFG
+    /// successor edges.
+    fn number_in_postorder(&mut self, entry: Ebb, cfg: &ControlFlowGraph) {
+        enum Step {
+            Enter(Ebb),
+            Leave(Ebb),
+        }
+
+        let mut stack = vec![Step::Enter(entry)];
+        let mut seen = HashSet::new();
+        seen.insert(entry);
+
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Enter(ebb) => {
+                    stack.push(Step::Leave(ebb));
+                    for &succ in cfg.successors(ebb) {
+                        if seen.insert(succ) {
+                            stack.push(Step::Enter(succ));
+                        }
+                    }
+                }
+                Step::Leave(ebb) => self.ebbs_postorder.push(ebb),
+            }
+        }
+    }
+
+    /// Walk up the idom chains of `a` and `b` by postorder number until they meet at their
+    /// common dominator.
+    ///
+    /// `rpo_number` gives the entry block 0 and increases moving away from it, so an immediate
+    /// dominator always has a smaller-or-equal number than its dominatee. Advance whichever
+    /// finger has the *larger* number up its idom chain until they meet.
+    fn intersect(&self, mut a: Ebb, mut b: Ebb) -> Ebb {
+        while a != b {
+            while self.rpo_number[&a] > self.rpo_number[&b] {
+                a = self.idom[&a];
+            }
+            while self.rpo_number[&b] > self.rpo_number[&a] {
+                b = self.idom[&b];
+            }
+        }
+        a
+    }
+
+    /// Does `a` dominate `b`? Every EBB dominates itself.
+    ///
+    /// Note: this only considers EBBs reachable from the entry block by the postorder walk in
+    /// `compute`. An EBB that isn't reachable (e.g. dead code left behind by a legalization pass
+    /// before DCE runs) has no entry in `idom`, so any dominance query naming it as `b` and a
+    /// different EBB as `a` falls through to `false` here, even when `a` is the entry block.
+    fn ebb_dominates(&self, a: Ebb, mut b: Ebb) -> bool {
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom.get(&b) {
+                Some(&idom) if idom != b => b = idom,
+                // `b` is the entry block, or wasn't reached by the postorder walk: nothing
+                // above it in the tree.
+                _ => return false,
+            }
+        }
+    }
+
+    /// Does the instruction `a` dominate the instruction `b`? An instruction dominates itself.
+    /// Uses in the same EBB as their definition must appear strictly later in layout order.
+    pub fn inst_dominates(&self, a: Inst, b: Inst, layout: &Layout) -> bool {
+        if a == b {
+            return true;
+        }
+        let ebb_a = layout.inst_ebb(a).expect("`a` must be inserted into the layout");
+        let ebb_b = layout.inst_ebb(b).expect("`b` must be inserted into the layout");
+        if ebb_a == ebb_b {
+            self.inst_seq[&a] < self.inst_seq[&b]
+        } else {
+            self.ebb_dominates(ebb_a, ebb_b)
+        }
+    }
+
+    /// The immediate dominator of `ebb`, or `None` if `ebb` is the entry block, or wasn't
+    /// reached by the control flow graph traversal.
+    pub fn idom(&self, ebb: Ebb) -> Option<Ebb> {
+        match self.idom.get(&ebb) {
+            Some(&idom) if idom != ebb => Some(idom),
+            _ => None,
+        }
+    }
+
+    /// Does EBB `a` dominate the EBB containing instruction `b`?
+    ///
+    /// EBB arguments are bound before the first instruction of their EBB runs, so they dominate
+    /// every instruction in their own EBB as well as every instruction in EBBs dominated by it.
+    /// This also covers the entry block: its arguments dominate every instruction in the
+    /// function, since the entry block dominates every other EBB by construction.
+    pub fn ebb_dominates_inst(&self, a: Ebb, b: Inst, layout: &Layout) -> bool {
+        if layout.entry_block() == Some(a) {
+            return true;
+        }
+        let ebb_b = layout.inst_ebb(b).expect("`b` must be inserted into the layout");
+        self.ebb_dominates(a, ebb_b)
+    }
+}
@@ -0,0 +1,107 @@
+//! A control flow graph tracked alongside the main function.
+//!
+//! This module defines a `ControlFlowGraph` struct that provides a mapping from EBBs to their
+//! predecessors and successors, computed from the branch and jump instructions that terminate
+//! each EBB in the function's layout.
+
+use ir::{Ebb, Function, Inst};
+use ir::instructions::InstructionData;
+use std::collections::HashMap;
+
+/// An edge into an EBB: the predecessor EBB together with the branch or jump instruction in it
+/// that transfers control to the successor.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct BasicBlock {
+    /// The predecessor EBB.
+    pub ebb: Ebb,
+    /// The terminator instruction in `ebb` that names the successor.
+    pub inst: Inst,
+}
+
+impl BasicBlock {
+    fn new(ebb: Ebb, inst: Inst) -> BasicBlock {
+        BasicBlock { ebb: ebb, inst: inst }
+    }
+}
+
+#[derive(Clone, Default)]
+struct CfgNode {
+    predecessors: Vec<BasicBlock>,
+    successors: Vec<Ebb>,
+}
+
+/// A control flow graph mapping every EBB in a function to its predecessor and successor EBBs.
+///
+/// The graph only tracks EBB-to-EBB edges. It is always computed fresh from a `Function`; it is
+/// never updated incrementally as the function is edited.
+pub struct ControlFlowGraph {
+    data: HashMap<Ebb, CfgNode>,
+}
+
+impl ControlFlowGraph {
+    /// Allocate a new blank control flow graph.
+    pub fn new() -> ControlFlowGraph {
+        ControlFlowGraph { data: HashMap::new() }
+    }
+
+    /// Build the control flow graph of `func`.
+    pub fn with_function(func: &Function) -> ControlFlowGraph {
+        let mut cfg = ControlFlowGraph::new();
+        cfg.compute(func);
+        cfg
+    }
+
+    /// Recompute this control flow graph from `func`, discarding any previous contents.
+    pub fn compute(&mut self, func: &Function) {
+        self.data.clear();
+
+        for ebb in func.layout.ebbs() {
+            self.data.insert(ebb, CfgNode::default());
+        }
+
+        for ebb in func.layout.ebbs() {
+            if let Some(inst) = func.layout.last_inst(ebb) {
+                for dest in branch_destinations(func, ebb, inst) {
+                    self.add_edge(ebb, inst, dest);
+                }
+            }
+        }
+    }
+
+    fn add_edge(&mut self, from: Ebb, inst: Inst, to: Ebb) {
+        self.data.entry(from).or_insert_with(CfgNode::default).successors.push(to);
+        self.data
+            .entry(to)
+            .or_insert_with(CfgNode::default)
+            .predecessors
+            .push(BasicBlock::new(from, inst));
+    }
+
+    /// The basic blocks that can branch or jump directly to `ebb`.
+    pub fn predecessors(&self, ebb: Ebb) -> &[BasicBlock] {
+        self.data.get(&ebb).map(|n| n.predecessors.as_slice()).unwrap_or(&[])
+    }
+
+    /// The EBBs that `ebb` can branch or jump directly to.
+    pub fn successors(&self, ebb: Ebb) -> &[Ebb] {
+        self.data.get(&ebb).map(|n| n.successors.as_slice()).unwrap_or(&[])
+    }
+}
+
+/// The EBBs that the terminator instruction of `ebb` can transfer control to: the explicit
+/// destination(s) of a `jump`/`branch`/`branch_table`, plus the fallthrough successor of a
+/// conditional `branch`.
+fn branch_destinations(func: &Function, ebb: Ebb, inst: Inst) -> Vec<Ebb> {
+    match func.dfg[inst] {
+        InstructionData::Jump { destination, .. } => vec![destination],
+        InstructionData::Branch { destination, .. } => {
+            let mut dests = vec![destination];
+            dests.extend(func.layout.next_ebb(ebb));
+            dests
+        }
+        InstructionData::BranchTable { table, .. } => {
+            func.jump_tables[table].iter().cloned().collect()
+        }
+        _ => Vec::new(),
+    }
+}
@@ -18,7 +18,6 @@
 //!
 //!   SSA form
 //!
-//! TODO:
 //!    - Values must be defined by an instruction that exists and that is inserted in
 //!      an EBB, or be an argument of an existing EBB.
 //!    - Values used by an instruction must dominate the instruction.
@@ -29,6 +28,10 @@
 //!    - All branches to an EBB must be present in the CFG.
 //!    - A recomputed dominator tree is identical to the existing one.
 //!
+//!   These checks are only performed by `verify_context`, which takes a `ControlFlowGraph` and
+//!   `DominatorTree` stored by the caller and cross-checks them against freshly recomputed ones.
+//!   `verify_function` does not have a stored CFG or dominator tree to check against.
+//!
 //!   Type checking
 //!
 //!    - Compare input and output values against the opcode's type constraints.
@@ -42,6 +45,14 @@
 //!    - All return instructions must have return value operands matching the current
 //!      function signature.
 //!
+//!   Global values
+//!
+//!    - `base` in `GlobalValueData::Load` and `GlobalValueData::IAddImm` must refer to an
+//!      existing `GlobalValue`.
+//!    - The chain of `base` references starting from any `GlobalValue` must not contain a
+//!      cycle, or legalization of `global_value` instructions (see `expand_global_value`) will
+//!      loop forever.
+//!
 //!   Ad hoc checking
 //!
 //!    - Stack slot loads and stores must be in-bounds.
@@ -53,9 +64,13 @@
 //!    - Swizzle and shuffle instructions take a variable number of lane arguments. The number
 //!      of arguments must match the destination type, and the lane indexes must be in range.
 
+use dominator_tree::DominatorTree;
+use flowgraph::{BasicBlock, ControlFlowGraph};
 use ir::{types, Function, ValueDef, Ebb, Inst, SigRef, FuncRef, ValueList, JumpTable, Value};
-use ir::instructions::InstructionFormat;
+use ir::{GlobalValue, GlobalValueData};
+use ir::instructions::{InstructionData, InstructionFormat, Opcode};
 use ir::entities::AnyEntity;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display, Formatter};
 use std::result;
 
@@ -99,6 +114,26 @@ pub fn verify_function(func: &Function) -> Result<()> {
     Verifier::new(func).run()
 }
 
+/// Verify `func`, additionally cross-checking the caller's `cfg` and `domtree` against freshly
+/// recomputed ones.
+pub fn verify_context(func: &Function,
+                       cfg: &ControlFlowGraph,
+                       domtree: &DominatorTree)
+                       -> Result<()> {
+    let verifier = Verifier::new(func);
+    verifier.typecheck_entry_block_params()?;
+    verifier.verify_global_values()?;
+    verifier.check_instructions()?;
+
+    // A single fresh recomputation, reused both for the SSA-dominance check below and for
+    // cross-checking the caller's `cfg`/`domtree` against it.
+    let computed_cfg = ControlFlowGraph::with_function(func);
+    let computed_domtree = DominatorTree::with_function(func, &computed_cfg);
+    verifier.check_ssa_dominance(&computed_domtree)?;
+    verifier.verify_cfg(cfg, &computed_cfg)?;
+    verifier.verify_dominator_tree(domtree, &computed_domtree)
+}
+
 struct Verifier<'a> {
     func: &'a Function,
 }
@@ -180,7 +215,9 @@ impl<'a> Verifier<'a> {
             }
         }
 
-        self.verify_entity_references(inst)
+        self.verify_entity_references(inst)?;
+        self.typecheck(inst)?;
+        self.typecheck_variable_args(inst)
     }
 
     fn verify_entity_references(&self, inst: Inst) -> Result<()> {
@@ -291,7 +328,333 @@ impl<'a> Verifier<'a> {
         }
     }
 
-    pub fn run(&self) -> Result<()> {
+    /// Type check the fixed operands and results of `inst` against the type constraints of its
+    /// opcode, resolving the controlling type variable from the operand that carries it for
+    /// polymorphic opcodes.
+    fn typecheck(&self, inst: Inst) -> Result<()> {
+        let inst_data = &self.func.dfg[inst];
+        let dfg = &self.func.dfg;
+        let constraints = inst_data.opcode().constraints();
+        let ctrl_type = dfg.ctrl_typevar(inst);
+
+        let fixed_args = constraints.fixed_value_arguments();
+        for (i, &arg) in inst_data.arguments(&dfg.value_lists)[..fixed_args].iter().enumerate() {
+            let arg_type = dfg.value_type(arg);
+            let expected_type = constraints.value_argument_type(i, ctrl_type);
+            if arg_type != expected_type {
+                return err!(inst,
+                            "arg {} ({}) has type {}, expected {}",
+                            i,
+                            arg,
+                            arg_type,
+                            expected_type);
+            }
+        }
+
+        // Variable results (e.g. `call`/`call_indirect`) come from the callee `Signature`
+        // instead of the opcode's constraints table; `typecheck_variable_args` checks those.
+        let fixed_results = constraints.fixed_results();
+        for (i, result) in dfg.inst_results(inst).take(fixed_results).enumerate() {
+            let result_type = dfg.value_type(result);
+            let expected_type = constraints.result_type(i, ctrl_type);
+            if result_type != expected_type {
+                return err!(inst,
+                            "result {} ({}) has type {}, expected {}",
+                            i,
+                            result,
+                            result_type,
+                            expected_type);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Type check the variable-length argument lists and result lists that are only resolvable
+    /// against another entity: branch/jump arguments against the destination EBB's parameters,
+    /// call arguments and results against the callee `Signature`, and `return` operands against
+    /// the current function's `Signature`.
+    fn typecheck_variable_args(&self, inst: Inst) -> Result<()> {
+        match &self.func.dfg[inst] {
+            &InstructionData::Jump { destination, ref args, .. } |
+            &InstructionData::Branch { destination, ref args, .. } => {
+                // Fixed operands (the tested value of a `brz`/`brnz`) are not passed to the
+                // destination EBB.
+                let fixed_args = self.func.dfg[inst].opcode().constraints().fixed_value_arguments();
+                let args = args.as_slice(&self.func.dfg.value_lists);
+                self.typecheck_block_call(inst, destination, &args[fixed_args..])?;
+            }
+            &InstructionData::BranchTable { table, .. } => {
+                for &ebb in self.func.jump_tables[table].iter() {
+                    if self.func.dfg.ebb_args(ebb).next().is_some() {
+                        return err!(inst, "takes no arguments in jump_table entry {}", ebb);
+                    }
+                }
+            }
+            &InstructionData::Call { func_ref, ref args, .. } => {
+                let sig = self.func.dfg.ext_funcs[func_ref].signature;
+                self.typecheck_call(inst, sig, args.as_slice(&self.func.dfg.value_lists))?;
+            }
+            &InstructionData::IndirectCall { sig_ref, ref args, .. } => {
+                // The first argument is the callee address, not part of the callee signature.
+                let args = args.as_slice(&self.func.dfg.value_lists);
+                self.typecheck_call(inst, sig_ref, &args[1..])?;
+            }
+            &InstructionData::MultiAry { opcode, ref args, .. } if opcode == Opcode::Return => {
+                self.typecheck_return(inst, args.as_slice(&self.func.dfg.value_lists))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn typecheck_block_call(&self, inst: Inst, destination: Ebb, args: &[Value]) -> Result<()> {
+        let params: Vec<Value> = self.func.dfg.ebb_args(destination).collect();
+        if args.len() != params.len() {
+            return err!(inst,
+                        "mismatched argument count for {}: {} given, {} expected",
+                        destination,
+                        args.len(),
+                        params.len());
+        }
+        for (i, (&arg, &param)) in args.iter().zip(params.iter()).enumerate() {
+            let arg_type = self.func.dfg.value_type(arg);
+            let param_type = self.func.dfg.value_type(param);
+            if arg_type != param_type {
+                return err!(inst,
+                            "arg {} ({}) has type {}, destination {} expects {}",
+                            i,
+                            arg,
+                            arg_type,
+                            destination,
+                            param_type);
+            }
+        }
+        Ok(())
+    }
+
+    fn typecheck_call(&self, inst: Inst, sig: SigRef, args: &[Value]) -> Result<()> {
+        let sig_data = &self.func.dfg.signatures[sig];
+
+        if args.len() != sig_data.argument_types.len() {
+            return err!(inst,
+                        "mismatched argument count for {}: {} given, {} expected",
+                        sig,
+                        args.len(),
+                        sig_data.argument_types.len());
+        }
+        for (i, (&arg, param)) in args.iter().zip(sig_data.argument_types.iter()).enumerate() {
+            let arg_type = self.func.dfg.value_type(arg);
+            if arg_type != param.value_type {
+                return err!(inst,
+                            "arg {} ({}) has type {}, signature {} expects {}",
+                            i,
+                            arg,
+                            arg_type,
+                            sig,
+                            param.value_type);
+            }
+        }
+
+        let results: Vec<Value> = self.func.dfg.inst_results(inst).collect();
+        if results.len() != sig_data.return_types.len() {
+            return err!(inst,
+                        "expected {} result values from {}, found {}",
+                        sig_data.return_types.len(),
+                        sig,
+                        results.len());
+        }
+        for (i, (&res, ret)) in results.iter().zip(sig_data.return_types.iter()).enumerate() {
+            let res_type = self.func.dfg.value_type(res);
+            if res_type != ret.value_type {
+                return err!(inst,
+                            "result {} ({}) has type {}, signature {} expects {}",
+                            i,
+                            res,
+                            res_type,
+                            sig,
+                            ret.value_type);
+            }
+        }
+        Ok(())
+    }
+
+    fn typecheck_return(&self, inst: Inst, args: &[Value]) -> Result<()> {
+        let expected = &self.func.signature.return_types;
+        if args.len() != expected.len() {
+            return err!(inst,
+                        "mismatched return values: {} given, {} expected by the function \
+                         signature",
+                        args.len(),
+                        expected.len());
+        }
+        for (i, (&arg, ret)) in args.iter().zip(expected.iter()).enumerate() {
+            let arg_type = self.func.dfg.value_type(arg);
+            if arg_type != ret.value_type {
+                return err!(inst,
+                            "return value {} ({}) has type {}, function signature expects {}",
+                            i,
+                            arg,
+                            arg_type,
+                            ret.value_type);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the entry block's parameters match the function signature.
+    fn typecheck_entry_block_params(&self) -> Result<()> {
+        let entry = match self.func.layout.entry_block() {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+
+        let expected = &self.func.signature.argument_types;
+        let params: Vec<Value> = self.func.dfg.ebb_args(entry).collect();
+        if params.len() != expected.len() {
+            return err!(entry,
+                        "entry block has {} parameters, function signature expects {}",
+                        params.len(),
+                        expected.len());
+        }
+        for (i, (&param, arg)) in params.iter().zip(expected.iter()).enumerate() {
+            let param_type = self.func.dfg.value_type(param);
+            if param_type != arg.value_type {
+                return err!(entry,
+                            "entry block parameter {} ({}) has type {}, function signature \
+                             expects {}",
+                            i,
+                            param,
+                            param_type,
+                            arg.value_type);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that every `base` referenced by a `GlobalValueData::Load` or
+    /// `GlobalValueData::IAddImm` names a valid `GlobalValue`, and that the `base` chains don't
+    /// contain a cycle.
+    ///
+    /// `GlobalValueData::Symbol` has no entity reference to validate here: its fields name an
+    /// external symbol directly rather than indexing into one of this function's own entity
+    /// maps, so there's no "invalid index" state for this verifier to catch. Symbol well-formedness
+    /// is a property of how the `ExternalName` itself was built, not something this pass can see.
+    fn verify_global_values(&self) -> Result<()> {
+        for gv in self.func.global_values.keys() {
+            match self.func.global_values[gv] {
+                GlobalValueData::Load { base, .. } |
+                GlobalValueData::IAddImm { base, .. } => {
+                    if !self.func.global_values.is_valid(base) {
+                        return err!(gv, "base {} is not a valid global value", base);
+                    }
+                }
+                GlobalValueData::VMContext | GlobalValueData::Symbol { .. } => {}
+            }
+        }
+
+        let mut colors = HashMap::new();
+        for gv in self.func.global_values.keys() {
+            self.verify_global_value_acyclic(gv, &mut colors)?;
+        }
+
+        Ok(())
+    }
+
+    /// DFS over the `base` edges of the global value dependency graph, coloring nodes white
+    /// (absent from `colors`), gray (on the current DFS stack) and black (fully explored).
+    /// Revisiting a gray node means its `base` chain cycles back on itself.
+    fn verify_global_value_acyclic(&self,
+                                    gv: GlobalValue,
+                                    colors: &mut HashMap<GlobalValue, bool>)
+                                    -> Result<()> {
+        match colors.get(&gv) {
+            Some(&true) => return Ok(()), // Black: already fully explored.
+            Some(&false) => return err!(gv, "global value forms a cycle through its base chain"),
+            None => {}
+        }
+
+        colors.insert(gv, false); // Gray: currently being explored.
+
+        let base = match self.func.global_values[gv] {
+            GlobalValueData::Load { base, .. } |
+            GlobalValueData::IAddImm { base, .. } => Some(base),
+            GlobalValueData::VMContext | GlobalValueData::Symbol { .. } => None,
+        };
+        if let Some(base) = base {
+            if self.func.global_values.is_valid(base) {
+                self.verify_global_value_acyclic(base, colors)?;
+            }
+        }
+
+        colors.insert(gv, true); // Black: fully explored, no cycle found through `gv`.
+        Ok(())
+    }
+
+    /// Check that every predecessor recorded in `cfg` is really a branch to its EBB and vice
+    /// versa, by comparing against `computed`, a `ControlFlowGraph` recomputed from scratch.
+    fn verify_cfg(&self, cfg: &ControlFlowGraph, computed: &ControlFlowGraph) -> Result<()> {
+        for ebb in self.func.layout.ebbs() {
+            let stored: HashSet<BasicBlock> = cfg.predecessors(ebb).iter().cloned().collect();
+            let recomputed: HashSet<BasicBlock> =
+                computed.predecessors(ebb).iter().cloned().collect();
+
+            if let Some(stale) = stored.difference(&recomputed).next() {
+                return err!(ebb,
+                            "cfg has stale predecessor {} that is not a branch to this ebb",
+                            stale.ebb);
+            }
+            if let Some(missing) = recomputed.difference(&stored).next() {
+                return err!(ebb,
+                            "cfg is missing predecessor {}, which branches to this ebb",
+                            missing.ebb);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that `domtree` agrees with `computed`, a dominator tree recomputed from scratch.
+    fn verify_dominator_tree(&self, domtree: &DominatorTree, computed: &DominatorTree) -> Result<()> {
+        for ebb in self.func.layout.ebbs() {
+            if domtree.idom(ebb) != computed.idom(ebb) {
+                return err!(ebb,
+                            "stored dominator tree disagrees with recomputed immediate \
+                             dominator for {}",
+                            ebb);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the value `v` used by `loc_inst` is defined "before" it, i.e. that its
+    /// definition dominates the use. See the module-level `SSA form` section.
+    fn verify_ssa_dominance(&self, domtree: &DominatorTree, loc_inst: Inst, v: Value) -> Result<()> {
+        match self.func.dfg.value_def(v) {
+            ValueDef::Result(def_inst, _) => {
+                if !domtree.inst_dominates(def_inst, loc_inst, &self.func.layout) {
+                    return err!(loc_inst,
+                                "uses value {} from {} that does not dominate it",
+                                v,
+                                def_inst);
+                }
+            }
+            ValueDef::Arg(def_ebb, _) => {
+                if !domtree.ebb_dominates_inst(def_ebb, loc_inst, &self.func.layout) {
+                    return err!(loc_inst,
+                                "uses value {} from {} that does not dominate it",
+                                v,
+                                def_ebb);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check EBB and instruction integrity (including entity references and type checking) for
+    /// every instruction in the function.
+    fn check_instructions(&self) -> Result<()> {
         for ebb in self.func.layout.ebbs() {
             for inst in self.func.layout.ebb_insts(ebb) {
                 self.ebb_integrity(ebb, inst)?;
@@ -300,12 +663,39 @@ impl<'a> Verifier<'a> {
         }
         Ok(())
     }
+
+    /// Check that every instruction argument in the function is dominated by its definition,
+    /// using the given `domtree`.
+    fn check_ssa_dominance(&self, domtree: &DominatorTree) -> Result<()> {
+        for ebb in self.func.layout.ebbs() {
+            for inst in self.func.layout.ebb_insts(ebb) {
+                for &arg in self.func.dfg[inst].arguments(&self.func.dfg.value_lists) {
+                    self.verify_ssa_dominance(domtree, inst, arg)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn run(&self) -> Result<()> {
+        self.typecheck_entry_block_params()?;
+        self.verify_global_values()?;
+        self.check_instructions()?;
+
+        // Entity references are now known to be valid, so it's safe to build the control flow
+        // graph and dominator tree needed to check SSA dominance below.
+        let cfg = ControlFlowGraph::with_function(self.func);
+        let domtree = DominatorTree::with_function(self.func, &cfg);
+        self.check_ssa_dominance(&domtree)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Verifier, Error};
-    use ir::Function;
+    use super::{verify_context, Verifier, Error};
+    use dominator_tree::DominatorTree;
+    use flowgraph::ControlFlowGraph;
+    use ir::{ArgumentType, Function, GlobalValueData, ValueList};
     use ir::instructions::{InstructionData, Opcode};
     use ir::types;
 
@@ -342,4 +732,263 @@ mod tests {
         let verifier = Verifier::new(&func);
         assert_err_with_msg!(verifier.run(), "instruction format");
     }
+
+    #[test]
+    fn ssa_dominance_ok() {
+        // ebb0(v0: i32) jumps to ebb1, which uses v0. The entry block's argument dominates
+        // every other EBB, so this is a valid use.
+        let mut func = Function::new();
+        func.signature.argument_types.push(ArgumentType::new(types::I32));
+        let ebb0 = func.dfg.make_ebb();
+        let v0 = func.dfg.append_ebb_param(ebb0, types::I32);
+        let ebb1 = func.dfg.make_ebb();
+
+        let jump = func.dfg.make_inst(InstructionData::Jump {
+                                           opcode: Opcode::Jump,
+                                           args: ValueList::new(),
+                                           destination: ebb1,
+                                       });
+
+        let add = func.dfg.make_inst(InstructionData::BinaryImm {
+                                          opcode: Opcode::IaddImm,
+                                          arg: v0,
+                                          imm: 1.into(),
+                                      });
+        func.dfg.append_result(add, types::I32);
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ValueList::new(),
+                                      });
+
+        func.layout.append_ebb(ebb0);
+        func.layout.append_inst(jump, ebb0);
+        func.layout.append_ebb(ebb1);
+        func.layout.append_inst(add, ebb1);
+        func.layout.append_inst(ret, ebb1);
+
+        let verifier = Verifier::new(&func);
+        assert_eq!(verifier.run(), Ok(()));
+    }
+
+    #[test]
+    fn ssa_dominance_violation_same_ebb() {
+        // `add` uses the value defined by `iconst`, but is laid out *before* it: a use that
+        // is not dominated by its definition.
+        let mut func = Function::new();
+        let ebb0 = func.dfg.make_ebb();
+
+        let iconst = func.dfg.make_inst(InstructionData::UnaryImm {
+                                             opcode: Opcode::Iconst,
+                                             imm: 42.into(),
+                                         });
+        let v0 = func.dfg.append_result(iconst, types::I32);
+
+        let add = func.dfg.make_inst(InstructionData::BinaryImm {
+                                          opcode: Opcode::IaddImm,
+                                          arg: v0,
+                                          imm: 1.into(),
+                                      });
+        func.dfg.append_result(add, types::I32);
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ValueList::new(),
+                                      });
+
+        func.layout.append_ebb(ebb0);
+        func.layout.append_inst(add, ebb0);
+        func.layout.append_inst(iconst, ebb0);
+        func.layout.append_inst(ret, ebb0);
+
+        let verifier = Verifier::new(&func);
+        assert_err_with_msg!(verifier.run(), "does not dominate");
+    }
+
+    #[test]
+    fn dominator_tree_diamond_merge() {
+        // entry brz's to ebb2 or falls through to ebb1, and both jump to ebb3: a genuine
+        // two-predecessor merge block, so computing idom(ebb3) calls `intersect` with two
+        // already-resolved idoms instead of the single-predecessor chains every other test in
+        // this file exercises.
+        let mut func = Function::new();
+        let entry = func.dfg.make_ebb();
+        func.signature.argument_types.push(ArgumentType::new(types::I32));
+        let v0 = func.dfg.append_ebb_param(entry, types::I32);
+        let ebb1 = func.dfg.make_ebb();
+        let ebb2 = func.dfg.make_ebb();
+        let ebb3 = func.dfg.make_ebb();
+
+        let mut brz_args = ValueList::new();
+        brz_args.push(v0, &mut func.dfg.value_lists);
+        let brz = func.dfg.make_inst(InstructionData::Branch {
+                                          opcode: Opcode::Brz,
+                                          args: brz_args,
+                                          destination: ebb2,
+                                      });
+        let jump1 = func.dfg.make_inst(InstructionData::Jump {
+                                            opcode: Opcode::Jump,
+                                            args: ValueList::new(),
+                                            destination: ebb3,
+                                        });
+        let jump2 = func.dfg.make_inst(InstructionData::Jump {
+                                            opcode: Opcode::Jump,
+                                            args: ValueList::new(),
+                                            destination: ebb3,
+                                        });
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ValueList::new(),
+                                      });
+
+        func.layout.append_ebb(entry);
+        func.layout.append_inst(brz, entry);
+        func.layout.append_ebb(ebb1);
+        func.layout.append_inst(jump1, ebb1);
+        func.layout.append_ebb(ebb2);
+        func.layout.append_inst(jump2, ebb2);
+        func.layout.append_ebb(ebb3);
+        func.layout.append_inst(ret, ebb3);
+
+        let verifier = Verifier::new(&func);
+        assert_eq!(verifier.run(), Ok(()));
+    }
+
+    #[test]
+    fn typecheck_entry_block_params_bad_type() {
+        // The signature says the entry block takes an `i32`, but it's declared with an `i64`.
+        let mut func = Function::new();
+        func.signature.argument_types.push(ArgumentType::new(types::I32));
+        let ebb0 = func.dfg.make_ebb();
+        func.dfg.append_ebb_param(ebb0, types::I64);
+        func.layout.append_ebb(ebb0);
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ValueList::new(),
+                                      });
+        func.layout.append_inst(ret, ebb0);
+
+        let verifier = Verifier::new(&func);
+        assert_err_with_msg!(verifier.run(), "has type");
+    }
+
+    #[test]
+    fn typecheck_call_indirect_with_result_ok() {
+        // Regression test: `call_indirect` has zero *fixed* results (its result types come
+        // from the callee signature), so typecheck must not walk past `fixed_results()` when
+        // checking a call's result types.
+        let mut func = Function::new();
+
+        let mut callee_sig = func.signature.clone();
+        callee_sig.return_types.push(ArgumentType::new(types::I32));
+        let sig_ref = func.dfg.signatures.push(callee_sig);
+
+        func.signature.argument_types.push(ArgumentType::new(types::I64));
+        let ebb0 = func.dfg.make_ebb();
+        let callee = func.dfg.append_ebb_param(ebb0, types::I64);
+
+        let mut call_args = ValueList::new();
+        call_args.push(callee, &mut func.dfg.value_lists);
+        let call_inst = func.dfg.make_inst(InstructionData::IndirectCall {
+                                                opcode: Opcode::CallIndirect,
+                                                sig_ref: sig_ref,
+                                                args: call_args,
+                                            });
+        let result = func.dfg.append_result(call_inst, types::I32);
+
+        func.signature.return_types.push(ArgumentType::new(types::I32));
+        let mut ret_args = ValueList::new();
+        ret_args.push(result, &mut func.dfg.value_lists);
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ret_args,
+                                      });
+
+        func.layout.append_ebb(ebb0);
+        func.layout.append_inst(call_inst, ebb0);
+        func.layout.append_inst(ret, ebb0);
+
+        let verifier = Verifier::new(&func);
+        assert_eq!(verifier.run(), Ok(()));
+    }
+
+    fn func_with_jump() -> Function {
+        // ebb0 jumps unconditionally to ebb1, which returns.
+        let mut func = Function::new();
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+
+        let jump = func.dfg.make_inst(InstructionData::Jump {
+                                           opcode: Opcode::Jump,
+                                           args: ValueList::new(),
+                                           destination: ebb1,
+                                       });
+        let ret = func.dfg.make_inst(InstructionData::MultiAry {
+                                          opcode: Opcode::Return,
+                                          args: ValueList::new(),
+                                      });
+
+        func.layout.append_ebb(ebb0);
+        func.layout.append_inst(jump, ebb0);
+        func.layout.append_ebb(ebb1);
+        func.layout.append_inst(ret, ebb1);
+        func
+    }
+
+    #[test]
+    fn verify_context_ok() {
+        let func = func_with_jump();
+        let cfg = ControlFlowGraph::with_function(&func);
+        let domtree = DominatorTree::with_function(&func, &cfg);
+        assert_eq!(verify_context(&func, &cfg, &domtree), Ok(()));
+    }
+
+    #[test]
+    fn verify_context_stale_cfg() {
+        // A blank cfg is missing the predecessor edge the jump actually creates.
+        let func = func_with_jump();
+        let cfg = ControlFlowGraph::new();
+        let domtree = DominatorTree::new();
+        assert_err_with_msg!(verify_context(&func, &cfg, &domtree), "cfg is missing predecessor");
+    }
+
+    #[test]
+    fn verify_context_stale_dominator_tree() {
+        // The real cfg agrees, but a blank domtree disagrees on ebb1's immediate dominator.
+        let func = func_with_jump();
+        let cfg = ControlFlowGraph::with_function(&func);
+        let domtree = DominatorTree::new();
+        assert_err_with_msg!(verify_context(&func, &cfg, &domtree), "disagrees with recomputed");
+    }
+
+    #[test]
+    fn global_value_invalid_base() {
+        // `dangling` is a valid GlobalValue in `scratch`'s own map, but not in `func`'s.
+        let mut scratch = Function::new();
+        scratch.global_values.push(GlobalValueData::VMContext);
+        let dangling = scratch.global_values.push(GlobalValueData::VMContext);
+
+        let mut func = Function::new();
+        func.global_values.push(GlobalValueData::IAddImm {
+                                     base: dangling,
+                                     offset: 0.into(),
+                                     global_type: types::I64,
+                                 });
+
+        let verifier = Verifier::new(&func);
+        assert_err_with_msg!(verifier.run(), "is not a valid global value");
+    }
+
+    #[test]
+    fn global_value_cycle() {
+        // A global value whose own base chain loops back to itself.
+        let mut func = Function::new();
+        let gv0 = func.global_values.push(GlobalValueData::VMContext);
+        func.global_values[gv0] = GlobalValueData::IAddImm {
+            base: gv0,
+            offset: 0.into(),
+            global_type: types::I64,
+        };
+
+        let verifier = Verifier::new(&func);
+        assert_err_with_msg!(verifier.run(), "cycle");
+    }
 }